@@ -0,0 +1,899 @@
+//! A small HTTP server that can run standalone or be embedded in another binary.
+//!
+//! Use [`build_router`] to get the application [`Router`] (for example to
+//! `merge` it with your own routes), or [`run`] to bind and serve it.
+
+mod server;
+
+use axum::{
+    extract::{ConnectInfo, OriginalUri, Request},
+    http::{header, HeaderName, HeaderValue, StatusCode, Uri},
+    middleware::{self, Next},
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+    Json, Router,
+};
+use ipnet::IpNet;
+use serde::{Deserialize, Deserializer};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tower::Layer;
+use tower_http::{
+    catch_panic::CatchPanicLayer,
+    services::{fs::ServeFileSystemResponseBody, ServeDir},
+    set_header::{SetResponseHeader, SetResponseHeaderLayer},
+    trace::TraceLayer,
+};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Config {
+    pub message: String,
+    /// TCP port to listen on; mutually exclusive with `unix_socket`.
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Unix domain socket path to listen on instead of TCP.
+    #[serde(default, deserialize_with = "deserialize_path")]
+    pub unix_socket: Option<PathBuf>,
+    /// Directory of site assets served under `/static`, separate from releases.
+    #[serde(default, deserialize_with = "deserialize_path")]
+    pub static_dir: Option<PathBuf>,
+    /// `Cache-Control` max-age for successful `/static` responses, in seconds.
+    #[serde(default = "default_static_max_age_secs")]
+    pub static_max_age_secs: u64,
+    /// TLS certificate chain. This binary can't terminate TLS, so setting it
+    /// is rejected at startup instead of silently serving plaintext.
+    #[serde(default, deserialize_with = "deserialize_path")]
+    pub tls_cert_path: Option<PathBuf>,
+    /// TLS private key; see `tls_cert_path`.
+    #[serde(default, deserialize_with = "deserialize_path")]
+    pub tls_key_path: Option<PathBuf>,
+    /// When set, `GET /` answers with a 302 to this absolute URL or `/` path
+    /// instead of `message`.
+    #[serde(default, deserialize_with = "deserialize_redirect")]
+    pub root_redirect: Option<HeaderValue>,
+    /// Only clients in one of these ranges are served; empty allows everyone.
+    #[serde(default)]
+    pub allow_cidrs: Vec<IpNet>,
+    /// Clients in these ranges are rejected, even if they are also allowed.
+    #[serde(default)]
+    pub deny_cidrs: Vec<IpNet>,
+    /// Adds the standard security headers to every response.
+    #[serde(default)]
+    pub security_headers: bool,
+    /// Extra headers set on every response, overriding the security defaults.
+    #[serde(default, deserialize_with = "deserialize_headers")]
+    pub response_headers: Vec<(HeaderName, HeaderValue)>,
+    /// Seconds an HTTP/1 client has to send a complete request head, which also
+    /// closes idle keep-alive connections. With `h2c` it also bounds the wait for
+    /// a connection's first request, and HTTP/2 peers that stop answering pings
+    /// are dropped after twice this. 0 disables the timeout.
+    #[serde(default = "default_io_timeout_secs")]
+    pub read_timeout_secs: u64,
+    /// Drops a connection that stalls on a write for this many seconds.
+    /// 0 disables the timeout.
+    #[serde(default = "default_io_timeout_secs")]
+    pub write_timeout_secs: u64,
+    /// Largest HTTP/1 request head accepted, in bytes; bigger ones get a 431.
+    #[serde(default = "default_max_header_bytes")]
+    pub max_header_bytes: usize,
+    /// Most HTTP/1 request headers accepted; more get a 431.
+    #[serde(default = "default_max_headers")]
+    pub max_headers: usize,
+    /// Upper bound on handling a request, also capping any `X-Request-Deadline`
+    /// the client sends. 0 disables it, the same as leaving it unset.
+    #[serde(default)]
+    pub request_timeout_secs: Option<u64>,
+    /// Seconds after startup during which `/health/ready` reports 503.
+    #[serde(default)]
+    pub readiness_delay_secs: u64,
+    /// Also accepts HTTP/2 with prior knowledge (h2c) on cleartext connections.
+    #[serde(default)]
+    pub h2c: bool,
+    /// How request paths with a trailing slash are routed. Paths under
+    /// `/static` are left alone, as static directories need the slash.
+    #[serde(default)]
+    pub trailing_slash: TrailingSlash,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingSlash {
+    /// `/health/` and `/health` are different routes.
+    #[default]
+    Strict,
+    /// `/health/` is answered with a 308 to `/health`.
+    Redirect,
+    /// `/health/` is routed as if it were `/health`.
+    Ignore,
+}
+
+fn default_io_timeout_secs() -> u64 {
+    60
+}
+
+fn default_static_max_age_secs() -> u64 {
+    3600
+}
+
+// Matches hyper's default read buffer limit
+fn default_max_header_bytes() -> usize {
+    8192 + 4096 * 100
+}
+
+// Matches hyper's default header count limit
+fn default_max_headers() -> usize {
+    100
+}
+
+impl Config {
+    /// Checks settings that can't be expressed in the config types alone.
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.port, &self.unix_socket) {
+            (Some(_), Some(_)) => {
+                return Err("port and unix_socket are mutually exclusive".to_string())
+            }
+            (None, None) => return Err("either port or unix_socket must be set".to_string()),
+            #[cfg(not(unix))]
+            (None, Some(_)) => {
+                return Err("unix_socket is only supported on Unix platforms".to_string())
+            }
+            _ => {}
+        }
+
+        match (&self.tls_cert_path, &self.tls_key_path) {
+            (Some(_), None) => {
+                return Err("tls_cert_path is set but tls_key_path is missing".to_string())
+            }
+            (None, Some(_)) => {
+                return Err("tls_key_path is set but tls_cert_path is missing".to_string())
+            }
+            (Some(_), Some(_)) => {
+                return Err(
+                    "TLS is configured but this binary was built without TLS support".to_string(),
+                )
+            }
+            (None, None) => {}
+        }
+
+        if let Some(dir) = &self.static_dir {
+            if !dir.is_dir() {
+                return Err(format!("static_dir {} is not a directory", dir.display()));
+            }
+        }
+
+        // hyper panics on read buffers smaller than this
+        if self.max_header_bytes < 8192 {
+            return Err("max_header_bytes must be at least 8192".to_string());
+        }
+        if self.max_headers == 0 {
+            return Err("max_headers must be at least 1".to_string());
+        }
+
+        if self.unix_socket.is_some()
+            && !(self.allow_cidrs.is_empty() && self.deny_cidrs.is_empty())
+        {
+            return Err(
+                "allow_cidrs and deny_cidrs need a TCP listener to know the client IP".to_string(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+pub struct AppState {
+    pub config: Config,
+    pub started_at: Instant,
+}
+
+impl AppState {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            started_at: Instant::now(),
+        }
+    }
+
+    fn readiness_delay(&self) -> Duration {
+        Duration::from_secs(self.config.readiness_delay_secs)
+    }
+}
+
+/// Builds the application router with all routes and middleware applied.
+///
+/// The returned router can be merged with other routers before serving.
+///
+/// With `allow_cidrs` or `deny_cidrs` set, every request needs the peer
+/// address, so serve it with
+/// `into_make_service_with_connect_info::<SocketAddr>()`. Without that, all
+/// requests are rejected with a 403.
+pub fn build_router(state: Arc<AppState>) -> Router {
+    // Build our application with routes
+    let mut app = Router::new().route("/", get(home_handler));
+
+    if let Some(dir) = &state.config.static_dir {
+        let static_files = Router::new()
+            .fallback_service(static_files(dir, state.config.static_max_age_secs))
+            .layer(middleware::from_fn(rebase_static_redirect));
+        app = app.nest_service(STATIC_PREFIX, static_files);
+    }
+
+    // Health checks are added after the deadline layer so probes never get a 503
+    // just because a deadline is configured
+    let app = app
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            enforce_deadline,
+        ))
+        .route("/health", get(health_handler))
+        .route("/health/ready", get(readiness_handler))
+        .with_state(state.clone());
+
+    // Normalization has to wrap the router so it runs before routing
+    let app = match state.config.trailing_slash {
+        TrailingSlash::Strict => app,
+        TrailingSlash::Redirect => Router::new()
+            .fallback_service(app)
+            .layer(middleware::from_fn(redirect_trailing_slash)),
+        TrailingSlash::Ignore => Router::new()
+            .fallback_service(app)
+            .layer(middleware::from_fn(trim_trailing_slash)),
+    };
+
+    let mut app = app.layer(CatchPanicLayer::custom(handle_panic));
+
+    if !state.config.allow_cidrs.is_empty() || !state.config.deny_cidrs.is_empty() {
+        app = app.layer(middleware::from_fn_with_state(
+            state.clone(),
+            enforce_ip_access,
+        ));
+    }
+
+    // Outside the trailing-slash wrapper, the panic handler and the IP check,
+    // so redirects, 500s and 403s get the headers too
+    if state.config.security_headers {
+        for (name, value) in security_headers() {
+            app = app.layer(SetResponseHeaderLayer::if_not_present(name, value));
+        }
+    }
+    for (name, value) in &state.config.response_headers {
+        app = app.layer(SetResponseHeaderLayer::overriding(
+            name.clone(),
+            value.clone(),
+        ));
+    }
+
+    app.layer(TraceLayer::new_for_http().make_span_with(make_request_span))
+}
+
+/// Binds the configured TCP port or Unix socket and serves the application
+/// until a shutdown signal is received.
+pub async fn run(config: Config) -> io::Result<()> {
+    config
+        .validate()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    let state = Arc::new(AppState::new(config));
+    let app = build_router(state.clone());
+
+    let readiness_delay = state.readiness_delay();
+    if !readiness_delay.is_zero() {
+        tokio::spawn(async move {
+            tokio::time::sleep(readiness_delay).await;
+            tracing::info!("Readiness delay of {:?} elapsed", readiness_delay);
+        });
+    }
+
+    #[cfg(unix)]
+    if let Some(path) = &state.config.unix_socket {
+        return server::serve_unix(path, app, &state.config).await;
+    }
+
+    let addr = format!("0.0.0.0:{}", state.config.port.unwrap_or_default());
+    server::serve_tcp(&addr, app, &state.config).await
+}
+
+const STATIC_PREFIX: &str = "/static";
+
+// ServeDir already refuses `..` segments, so requests can't escape `dir`
+fn static_files(
+    dir: &std::path::Path,
+    max_age_secs: u64,
+) -> SetResponseHeader<
+    ServeDir,
+    impl Fn(&Response<ServeFileSystemResponseBody>) -> Option<HeaderValue> + Clone,
+> {
+    let cache_control = HeaderValue::try_from(format!("public, max-age={}", max_age_secs))
+        .expect("formatted Cache-Control is a valid header value");
+
+    // Only successful responses are cacheable, so a missing asset isn't cached as a 404.
+    // A 304 has to repeat it, or revalidating would leave the client without a max-age.
+    SetResponseHeaderLayer::if_not_present(
+        header::CACHE_CONTROL,
+        move |response: &Response<ServeFileSystemResponseBody>| {
+            let status = response.status();
+            (status.is_success() || status == StatusCode::NOT_MODIFIED)
+                .then(|| cache_control.clone())
+        },
+    )
+    .layer(ServeDir::new(dir))
+}
+
+// Same span as the default TraceLayer one, plus the peer address when served over TCP
+fn make_request_span(request: &Request) -> tracing::Span {
+    // Under the default one's target too, so filters that enable tower_http keep it
+    let span = tracing::debug_span!(
+        target: "tower_http::trace::make_span",
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        version = ?request.version(),
+        remote_addr = tracing::field::Empty,
+    );
+    if let Some(ConnectInfo(addr)) = request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        span.record("remote_addr", tracing::field::display(addr));
+    }
+    span
+}
+
+async fn home_handler(
+    axum::extract::State(state): axum::extract::State<Arc<AppState>>,
+) -> Response {
+    match &state.config.root_redirect {
+        Some(location) => {
+            (StatusCode::FOUND, [(header::LOCATION, location.clone())]).into_response()
+        }
+        None => state.config.message.clone().into_response(),
+    }
+}
+
+async fn health_handler() -> impl IntoResponse {
+    (StatusCode::OK, "OK")
+}
+
+async fn readiness_handler(
+    axum::extract::State(state): axum::extract::State<Arc<AppState>>,
+) -> impl IntoResponse {
+    if state.started_at.elapsed() < state.readiness_delay() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "NOT READY");
+    }
+    (StatusCode::OK, "OK")
+}
+
+/// Rejects clients outside `allow_cidrs` or inside `deny_cidrs` with a 403.
+async fn enforce_ip_access(
+    axum::extract::State(state): axum::extract::State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client_ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    match client_ip {
+        Some(ip) if ip_allowed(&state.config, ip) => next.run(request).await,
+        Some(ip) => {
+            tracing::debug!("Rejected request from {}", ip);
+            StatusCode::FORBIDDEN.into_response()
+        }
+        // Without a peer address there is no way to check the lists, so fail closed
+        None => {
+            tracing::warn!("Rejected request without a client address");
+            StatusCode::FORBIDDEN.into_response()
+        }
+    }
+}
+
+fn ip_allowed(config: &Config, ip: IpAddr) -> bool {
+    // An IPv4 client on a dual-stack socket shows up as ::ffff:a.b.c.d
+    let ip = ip.to_canonical();
+    if config.deny_cidrs.iter().any(|net| net.contains(&ip)) {
+        return false;
+    }
+    config.allow_cidrs.is_empty() || config.allow_cidrs.iter().any(|net| net.contains(&ip))
+}
+
+const REQUEST_DEADLINE: HeaderName = HeaderName::from_static("x-request-deadline");
+
+/// Aborts the handler once the request deadline passes: the earlier of
+/// `X-Request-Deadline` (absolute unix time in milliseconds), when the client
+/// sends one, and `request_timeout_secs`.
+async fn enforce_deadline(
+    axum::extract::State(state): axum::extract::State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let requested = request_deadline(&request).map(|deadline| {
+        deadline
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO)
+    });
+    let configured = state
+        .config
+        .request_timeout_secs
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs);
+    // A client can shorten the server's limit but never extend it
+    let remaining = match (requested, configured) {
+        (Some(requested), Some(configured)) => requested.min(configured),
+        (Some(remaining), None) | (None, Some(remaining)) => remaining,
+        (None, None) => return next.run(request).await,
+    };
+
+    if remaining.is_zero() {
+        return deadline_exceeded();
+    }
+    tokio::time::timeout(remaining, next.run(request))
+        .await
+        .unwrap_or_else(|_| deadline_exceeded())
+}
+
+// A malformed header is ignored rather than rejected, as if it wasn't sent
+fn request_deadline(request: &Request) -> Option<SystemTime> {
+    let millis = request
+        .headers()
+        .get(REQUEST_DEADLINE)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    UNIX_EPOCH.checked_add(Duration::from_millis(millis))
+}
+
+fn deadline_exceeded() -> Response {
+    (StatusCode::SERVICE_UNAVAILABLE, "deadline exceeded").into_response()
+}
+
+// ServeDir redirects a directory to its path plus a slash, but only sees the
+// path below the mount point, so put the prefix back
+async fn rebase_static_redirect(
+    OriginalUri(original): OriginalUri,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    if response.status() == StatusCode::TEMPORARY_REDIRECT {
+        let mut location = format!("{}/", original.path());
+        if let Some(query) = original.query() {
+            location.push('?');
+            location.push_str(query);
+        }
+        if let Ok(location) = HeaderValue::try_from(location) {
+            response.headers_mut().insert(header::LOCATION, location);
+        }
+    }
+    response
+}
+
+// Static paths are left alone, as directories are only served with the slash
+fn without_trailing_slash(uri: &Uri) -> Option<String> {
+    let path = uri.path();
+    let is_static = path
+        .strip_prefix(STATIC_PREFIX)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+    if path.len() <= 1 || !path.ends_with('/') || is_static {
+        return None;
+    }
+
+    // Collapse leading slashes too, so `//host/` can't become an off-site redirect
+    let mut trimmed = format!("/{}", path.trim_matches('/'));
+    if let Some(query) = uri.query() {
+        trimmed.push('?');
+        trimmed.push_str(query);
+    }
+    Some(trimmed)
+}
+
+async fn redirect_trailing_slash(request: Request, next: Next) -> Response {
+    match without_trailing_slash(request.uri()) {
+        Some(location) => Redirect::permanent(&location).into_response(),
+        None => next.run(request).await,
+    }
+}
+
+async fn trim_trailing_slash(mut request: Request, next: Next) -> Response {
+    if let Some(trimmed) = without_trailing_slash(request.uri()) {
+        let mut parts = request.uri().clone().into_parts();
+        parts.path_and_query = trimmed.parse().ok();
+        if let Ok(uri) = Uri::from_parts(parts) {
+            *request.uri_mut() = uri;
+        }
+    }
+    next.run(request).await
+}
+
+// Not logged here: the panic hook has already reported it, with the backtrace
+// in the binary and on stderr by default for embedders
+fn handle_panic(_: Box<dyn Any + Send + 'static>) -> Response {
+    let status = StatusCode::INTERNAL_SERVER_ERROR;
+    let body = serde_json::json!({
+        "error": {
+            "code": status.as_u16(),
+            "message": "internal server error",
+        }
+    });
+
+    (status, Json(body)).into_response()
+}
+
+fn security_headers() -> [(HeaderName, HeaderValue); 3] {
+    [
+        (
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        ),
+        (header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY")),
+        (
+            header::REFERRER_POLICY,
+            HeaderValue::from_static("no-referrer"),
+        ),
+    ]
+}
+
+fn deserialize_headers<'de, D>(deserializer: D) -> Result<Vec<(HeaderName, HeaderValue)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    BTreeMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, value)| {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| Error::custom(format!("invalid header name {:?}: {}", name, e)))?;
+            let header_value = HeaderValue::from_str(&value).map_err(|e| {
+                Error::custom(format!("invalid value for header {:?}: {}", name, e))
+            })?;
+            Ok((header_name, header_value))
+        })
+        .collect()
+}
+
+fn deserialize_redirect<'de, D>(deserializer: D) -> Result<Option<HeaderValue>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let Some(target) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    if target.trim().is_empty() {
+        return Err(Error::custom("root_redirect must not be empty"));
+    }
+    let uri = target
+        .parse::<axum::http::Uri>()
+        .map_err(|e| Error::custom(format!("invalid root_redirect {:?}: {}", target, e)))?;
+    // `example.com` parses as an authority alone, which a browser would resolve
+    // as a relative path
+    let absolute = uri.scheme().is_some() && uri.authority().is_some();
+    if !absolute && !target.starts_with('/') {
+        return Err(Error::custom(format!(
+            "invalid root_redirect {:?}: expected an absolute URL or a path starting with /",
+            target
+        )));
+    }
+    HeaderValue::from_str(&target)
+        .map(Some)
+        .map_err(|e| Error::custom(format!("invalid root_redirect {:?}: {}", target, e)))
+}
+
+// Path-typed settings may reference environment variables, see `expand_env_vars`
+fn deserialize_path<'de, D>(deserializer: D) -> Result<Option<PathBuf>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|path| {
+            expand_env_vars(&path)
+                .map(PathBuf::from)
+                .map_err(serde::de::Error::custom)
+        })
+        .transpose()
+}
+
+/// Expands `${VAR}` and `${VAR:-default}` references from the environment.
+///
+/// As in the shell, the default is used when the variable is unset or empty.
+/// A reference to an unset variable without a default is an error.
+fn expand_env_vars(input: &str) -> Result<String, String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let reference = &rest[start + 2..];
+        let end = reference
+            .find('}')
+            .ok_or_else(|| format!("unterminated variable reference in {:?}", input))?;
+        let (name, default) = match reference[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&reference[..end], None),
+        };
+        if name.is_empty() {
+            return Err(format!("empty variable reference in {:?}", input));
+        }
+
+        match (std::env::var(name), default) {
+            (Ok(value), Some(default)) if value.is_empty() => output.push_str(default),
+            (Ok(value), _) => output.push_str(&value),
+            (Err(std::env::VarError::NotPresent), Some(default)) => output.push_str(default),
+            (Err(e), _) => {
+                return Err(format!(
+                    "environment variable {} referenced in {:?}: {}",
+                    name, input, e
+                ))
+            }
+        }
+        rest = &reference[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
+    use std::env;
+
+    // Compute possible config paths based on the running binary and current directory
+    let mut config_paths = Vec::new();
+
+    // 1. Try config/config.json relative to the executable
+    if let Ok(exe_path) = env::current_exe() {
+        if let Some(exe_dir) = exe_path.parent() {
+            config_paths.push(exe_dir.join("config/config.json"));
+            config_paths.push(exe_dir.join("../config/config.json"));
+        }
+    }
+
+    // 2. Try config/config.json relative to the current working directory
+    if let Ok(cwd) = env::current_dir() {
+        config_paths.push(cwd.join("config/config.json"));
+        config_paths.push(cwd.join("../config/config.json"));
+        config_paths.push(cwd.join("config.json"));
+    }
+
+    // 3. Try config.json in the same directory as the executable
+    if let Ok(exe_path) = env::current_exe() {
+        if let Some(exe_dir) = exe_path.parent() {
+            config_paths.push(exe_dir.join("config.json"));
+        }
+    }
+
+    // 4. Fallback: just "config.json" in the current directory
+    config_paths.push(PathBuf::from("config.json"));
+
+    let mut last_error = None;
+    let mut chosen = None;
+    // One line per candidate so it's obvious which file won and what it shadowed
+    let mut resolution = Vec::with_capacity(config_paths.len());
+
+    for config_path in config_paths {
+        if chosen.is_some() {
+            let shadowed = if config_path.exists() {
+                " (exists, shadowed)"
+            } else {
+                ""
+            };
+            resolution.push(format!("  skipped {}{}", config_path.display(), shadowed));
+            continue;
+        }
+
+        tracing::info!("Trying config path: {}", config_path.display());
+        if config_path.exists() {
+            match fs::read_to_string(&config_path) {
+                Ok(config_str) => {
+                    resolution.push(format!("  ✓ {}", config_path.display()));
+                    chosen = Some((config_path, config_str));
+                }
+                Err(e) => {
+                    resolution.push(format!("  ✗ {}: {}", config_path.display(), e));
+                    last_error = Some(format!("{}: {}", config_path.display(), e));
+                    continue;
+                }
+            }
+        } else {
+            resolution.push(format!("  ✗ {}: not found", config_path.display()));
+            last_error = Some(format!("{}: not found", config_path.display()));
+        }
+    }
+
+    tracing::info!("Config path resolution:\n{}", resolution.join("\n"));
+
+    if let Some((config_path, config_str)) = chosen {
+        let config: Config = serde_json::from_str(&config_str)
+            .map_err(|e| format!("Failed to parse {}: {}", config_path.display(), e))?;
+        config
+            .validate()
+            .map_err(|e| format!("Invalid config {}: {}", config_path.display(), e))?;
+        tracing::info!("Loaded config from: {}", config_path.display());
+        return Ok(config);
+    }
+
+    Err(format!(
+        "Failed to load config from any path. Last error: {}",
+        last_error.unwrap_or_default()
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip_config(allow: &[&str], deny: &[&str]) -> Config {
+        serde_json::from_value(serde_json::json!({
+            "message": "hello",
+            "allow_cidrs": allow,
+            "deny_cidrs": deny,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn ip_allowed_prefers_deny_over_allow() {
+        let config = ip_config(&["10.0.0.0/8"], &["10.1.0.0/16"]);
+        assert!(ip_allowed(&config, "10.2.3.4".parse().unwrap()));
+        assert!(!ip_allowed(&config, "10.1.2.3".parse().unwrap()));
+        assert!(!ip_allowed(&config, "192.168.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn ip_allowed_with_empty_allow_list_admits_everyone_not_denied() {
+        let config = ip_config(&[], &["192.168.0.0/16"]);
+        assert!(ip_allowed(&config, "10.0.0.1".parse().unwrap()));
+        assert!(ip_allowed(&config, "::1".parse().unwrap()));
+        assert!(!ip_allowed(&config, "192.168.1.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn ip_allowed_matches_ipv4_mapped_addresses() {
+        let config = ip_config(&["10.0.0.0/8"], &["10.1.0.0/16"]);
+        assert!(ip_allowed(&config, "::ffff:10.2.3.4".parse().unwrap()));
+        assert!(!ip_allowed(&config, "::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!ip_allowed(&config, "::ffff:192.168.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn expand_env_vars_substitutes_references() {
+        std::env::set_var("EXPAND_TEST_HOST", "example.com");
+        std::env::set_var("EXPAND_TEST_PORT", "8080");
+        assert_eq!(
+            expand_env_vars("http://${EXPAND_TEST_HOST}:${EXPAND_TEST_PORT}/").unwrap(),
+            "http://example.com:8080/"
+        );
+        assert_eq!(expand_env_vars("no references").unwrap(), "no references");
+    }
+
+    #[test]
+    fn expand_env_vars_uses_default_when_unset_or_empty() {
+        std::env::remove_var("EXPAND_TEST_UNSET");
+        std::env::set_var("EXPAND_TEST_EMPTY", "");
+        assert_eq!(
+            expand_env_vars("${EXPAND_TEST_UNSET:-fallback}").unwrap(),
+            "fallback"
+        );
+        assert_eq!(
+            expand_env_vars("${EXPAND_TEST_EMPTY:-fallback}").unwrap(),
+            "fallback"
+        );
+        assert_eq!(expand_env_vars("${EXPAND_TEST_EMPTY}").unwrap(), "");
+    }
+
+    #[test]
+    fn expand_env_vars_rejects_bad_references() {
+        std::env::remove_var("EXPAND_TEST_MISSING");
+        assert!(expand_env_vars("${EXPAND_TEST_MISSING}")
+            .unwrap_err()
+            .contains("EXPAND_TEST_MISSING"));
+        assert!(expand_env_vars("${UNTERMINATED")
+            .unwrap_err()
+            .contains("unterminated"));
+        assert!(expand_env_vars("${}").unwrap_err().contains("empty"));
+        assert!(expand_env_vars("${:-default}")
+            .unwrap_err()
+            .contains("empty"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn expand_env_vars_rejects_non_unicode_values() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let value = std::ffi::OsString::from_vec(vec![0x66, 0x6f, 0x80]);
+        std::env::set_var("EXPAND_TEST_NOT_UNICODE", value);
+        // Even with a default, a value that is set shouldn't be silently replaced
+        let err = expand_env_vars("${EXPAND_TEST_NOT_UNICODE:-fallback}").unwrap_err();
+        assert!(err.contains("EXPAND_TEST_NOT_UNICODE"), "{}", err);
+        assert!(err.contains("valid unicode"), "{}", err);
+    }
+
+    fn tls_config(cert: Option<&str>, key: Option<&str>) -> Config {
+        serde_json::from_value(serde_json::json!({
+            "message": "hello",
+            "port": 8080,
+            "tls_cert_path": cert,
+            "tls_key_path": key,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn validate_rejects_partial_tls_settings() {
+        let err = tls_config(Some("cert.pem"), None).validate().unwrap_err();
+        assert!(err.contains("tls_key_path is missing"), "{}", err);
+
+        let err = tls_config(None, Some("key.pem")).validate().unwrap_err();
+        assert!(err.contains("tls_cert_path is missing"), "{}", err);
+    }
+
+    #[test]
+    fn validate_rejects_tls_without_tls_support() {
+        let err = tls_config(Some("cert.pem"), Some("key.pem"))
+            .validate()
+            .unwrap_err();
+        assert!(err.contains("without TLS support"), "{}", err);
+
+        assert!(tls_config(None, None).validate().is_ok());
+    }
+
+    fn redirect_config(target: &str) -> Result<Config, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({
+            "message": "hello",
+            "root_redirect": target,
+        }))
+    }
+
+    #[test]
+    fn root_redirect_accepts_paths_and_absolute_urls() {
+        for target in [
+            "/releases",
+            "/releases?os=linux",
+            "https://example.com/releases",
+        ] {
+            let config = redirect_config(target).unwrap();
+            assert_eq!(config.root_redirect.unwrap(), target);
+        }
+    }
+
+    #[test]
+    fn root_redirect_rejects_empty_and_scheme_less_targets() {
+        for target in ["", "  ", "example.com", "example.com/releases", "releases"] {
+            let err = redirect_config(target).unwrap_err().to_string();
+            assert!(err.contains("root_redirect"), "{:?}: {}", target, err);
+        }
+    }
+
+    fn trimmed(uri: &str) -> Option<String> {
+        without_trailing_slash(&uri.parse().unwrap())
+    }
+
+    #[test]
+    fn without_trailing_slash_trims_and_keeps_the_query() {
+        assert_eq!(trimmed("/health/").as_deref(), Some("/health"));
+        assert_eq!(trimmed("/a/?q=1").as_deref(), Some("/a?q=1"));
+        assert_eq!(trimmed("/health"), None);
+        assert_eq!(trimmed("/"), None);
+    }
+
+    #[test]
+    fn without_trailing_slash_never_yields_an_off_site_path() {
+        assert_eq!(trimmed("//evil.com/").as_deref(), Some("/evil.com"));
+        assert_eq!(trimmed("///").as_deref(), Some("/"));
+    }
+
+    #[test]
+    fn without_trailing_slash_leaves_static_paths_alone() {
+        assert_eq!(trimmed("/static/x/"), None);
+        assert_eq!(trimmed("/static/"), None);
+        assert_eq!(trimmed("/statics/").as_deref(), Some("/statics"));
+    }
+}
@@ -0,0 +1,290 @@
+//! Accept loop shared by the TCP and Unix socket listeners.
+
+use crate::Config;
+use axum::{extract::ConnectInfo, Router};
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::{conn::auto, graceful::GracefulShutdown};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpListener;
+use tokio::time::Sleep;
+use tokio_io_timeout::TimeoutStream;
+use tower::Service;
+
+/// A bound listener the accept loop can pull connections from.
+pub(crate) trait Listener {
+    type Io: AsyncRead + AsyncWrite + Send + Unpin + 'static;
+
+    /// Accepts a connection, along with the peer address when there is one.
+    fn accept(&self) -> impl Future<Output = io::Result<(Self::Io, Option<SocketAddr>)>> + Send;
+}
+
+impl Listener for TcpListener {
+    type Io = tokio::net::TcpStream;
+
+    async fn accept(&self) -> io::Result<(Self::Io, Option<SocketAddr>)> {
+        let (stream, addr) = TcpListener::accept(self).await?;
+        Ok((stream, Some(addr)))
+    }
+}
+
+#[cfg(unix)]
+impl Listener for tokio::net::UnixListener {
+    type Io = tokio::net::UnixStream;
+
+    async fn accept(&self) -> io::Result<(Self::Io, Option<SocketAddr>)> {
+        let (stream, _) = tokio::net::UnixListener::accept(self).await?;
+        Ok((stream, None))
+    }
+}
+
+pub(crate) async fn serve_tcp(addr: &str, app: Router, config: &Config) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await.map_err(|err| {
+        io::Error::new(err.kind(), format!("Failed to bind to {}: {}", addr, err))
+    })?;
+
+    tracing::info!("Server listening on {}", addr);
+
+    serve(listener, app, config).await;
+    Ok(())
+}
+
+#[cfg(unix)]
+pub(crate) async fn serve_unix(
+    path: &std::path::Path,
+    app: Router,
+    config: &Config,
+) -> io::Result<()> {
+    use std::fs;
+    use std::os::unix::fs::FileTypeExt;
+
+    // Clean up a socket left behind by a previous run, but never anything else
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            // A live socket belongs to another running instance; taking it over
+            // would leave that one unreachable and let either delete it on exit
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("Failed to bind to {}: address in use", path.display()),
+                ));
+            }
+            tracing::info!("Removing stale socket: {}", path.display());
+            fs::remove_file(path)?;
+        }
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Failed to bind to {}: not a socket", path.display()),
+            ));
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+
+    let listener = tokio::net::UnixListener::bind(path).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("Failed to bind to {}: {}", path.display(), err),
+        )
+    })?;
+
+    tracing::info!("Server listening on {}", path.display());
+
+    serve(listener, app, config).await;
+
+    if let Err(err) = fs::remove_file(path) {
+        tracing::warn!("Failed to remove socket {}: {}", path.display(), err);
+    }
+
+    Ok(())
+}
+
+/// Serves connections from `listener` until a shutdown signal arrives, then
+/// waits for in-flight connections to finish.
+async fn serve<L: Listener>(listener: L, app: Router, config: &Config) {
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+    if !config.h2c {
+        builder = builder.http1_only();
+    }
+    builder.http1().max_buf_size(config.max_header_bytes);
+    // Setting this at all moves header parsing to the heap, so only do it when needed
+    if config.max_headers != crate::default_max_headers() {
+        builder.http1().max_headers(config.max_headers);
+    }
+
+    // Only the request head is under a deadline: an IO read timeout would also
+    // fire while a long response is being written, as hyper keeps a read pending
+    let read_timeout = io_timeout(config.read_timeout_secs);
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .header_read_timeout(read_timeout);
+    // HTTP/2 has no such deadline, so drop idle peers that stop answering pings
+    if let (true, Some(timeout)) = (config.h2c, read_timeout) {
+        builder
+            .http2()
+            .timer(TokioTimer::new())
+            .keep_alive_interval(timeout)
+            .keep_alive_timeout(timeout);
+    }
+    let write_timeout = io_timeout(config.write_timeout_secs);
+
+    let graceful = GracefulShutdown::new();
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    loop {
+        let (stream, remote_addr) = tokio::select! {
+            result = listener.accept() => match result {
+                Ok(accepted) => accepted,
+                Err(err) => {
+                    tracing::error!("Failed to accept connection: {}", err);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+
+        // Per-write timeout, so a peer that stops reading can't hold the connection
+        let mut stream = TimeoutStream::new(stream);
+        stream.set_write_timeout(write_timeout);
+
+        // With h2c, sniffing the protocol and the HTTP/2 handshake come before
+        // any of hyper's deadlines, so bound the wait for the first request
+        let first_request = Arc::new(AtomicBool::new(false));
+        let stream = FirstRequestTimeout {
+            io: Box::pin(stream),
+            deadline: read_timeout
+                .filter(|_| config.h2c)
+                .map(|timeout| Box::pin(tokio::time::sleep(timeout))),
+            first_request: first_request.clone(),
+        };
+
+        let tower_service = app.clone();
+        let hyper_service =
+            hyper::service::service_fn(move |mut request: hyper::Request<Incoming>| {
+                first_request.store(true, Ordering::Relaxed);
+                if let Some(addr) = remote_addr {
+                    request.extensions_mut().insert(ConnectInfo(addr));
+                }
+                tower_service.clone().call(request)
+            });
+        let connection = builder
+            .serve_connection(TokioIo::new(stream), hyper_service)
+            .into_owned();
+        let connection = graceful.watch(connection);
+
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                tracing::debug!("Connection error: {}", err);
+            }
+        });
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+}
+
+/// Fails reads once `deadline` passes, unless the connection has received its
+/// first request by then.
+struct FirstRequestTimeout<I> {
+    io: I,
+    deadline: Option<Pin<Box<Sleep>>>,
+    first_request: Arc<AtomicBool>,
+}
+
+impl<I: AsyncRead + Unpin> AsyncRead for FirstRequestTimeout<I> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.first_request.load(Ordering::Relaxed) {
+            self.deadline = None;
+        }
+        if let Some(deadline) = &mut self.deadline {
+            if deadline.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "no request within the read timeout",
+                )));
+            }
+        }
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl<I: AsyncWrite + Unpin> AsyncWrite for FirstRequestTimeout<I> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+fn io_timeout(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("Shutting down");
+}
@@ -0,0 +1,55 @@
+use binary_release_server::{run, Config};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+/// A socket path under a per-process temp directory.
+pub fn socket_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("binary-release-server-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(format!("{}.sock", name))
+}
+
+/// Builds a minimal config listening on `socket`, with `settings` merged in.
+pub fn config(socket: &Path, settings: serde_json::Value) -> Config {
+    let mut config = serde_json::json!({
+        "message": "hello",
+        "unix_socket": socket,
+    });
+    config
+        .as_object_mut()
+        .unwrap()
+        .extend(settings.as_object().unwrap().clone());
+    serde_json::from_value(config).unwrap()
+}
+
+/// Starts the server on a fresh Unix socket and waits until it accepts
+/// connections.
+pub async fn start(name: &str, settings: serde_json::Value) -> PathBuf {
+    let socket = socket_path(name);
+    tokio::spawn(run(config(&socket, settings)));
+    drop(connect(&socket).await);
+    socket
+}
+
+pub async fn connect(socket: &Path) -> UnixStream {
+    for _ in 0..100 {
+        if let Ok(stream) = UnixStream::connect(socket).await {
+            return stream;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("server did not start listening on {}", socket.display());
+}
+
+/// Sends a raw request on a new connection and returns the whole response.
+#[allow(dead_code)]
+pub async fn send(socket: &Path, request: &str) -> String {
+    let mut client = connect(socket).await;
+    client.write_all(request.as_bytes()).await.unwrap();
+    let mut response = Vec::new();
+    // The server may close before reading everything we sent
+    client.read_to_end(&mut response).await.ok();
+    String::from_utf8_lossy(&response).into_owned()
+}
@@ -0,0 +1,142 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const LARGE_FILE_LEN: usize = 8 * 1024 * 1024;
+
+/// A static directory holding `large.bin`, big enough not to fit in the socket
+/// buffers.
+fn large_file_dir(name: &str) -> PathBuf {
+    let dir = common::socket_path(name).with_extension("static");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("large.bin"), vec![b'x'; LARGE_FILE_LEN]).unwrap();
+    dir
+}
+
+const LARGE_FILE_REQUEST: &[u8] =
+    b"GET /static/large.bin HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+
+#[tokio::test]
+async fn stalled_client_is_dropped_after_read_timeout() {
+    let socket = common::start(
+        "read-timeout",
+        serde_json::json!({ "read_timeout_secs": 1 }),
+    )
+    .await;
+    let mut client = common::connect(&socket).await;
+
+    // Start a request but never finish the header block
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n")
+        .await
+        .unwrap();
+
+    let started = Instant::now();
+    let mut response = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+        .await
+        .expect("connection was not dropped")
+        .ok();
+
+    assert!(started.elapsed() >= Duration::from_millis(900));
+    assert!(response.is_empty());
+}
+
+#[tokio::test]
+async fn slow_client_within_read_timeout_is_served() {
+    let socket = common::start("slow-client", serde_json::json!({ "read_timeout_secs": 2 })).await;
+    let mut client = common::connect(&socket).await;
+
+    // Trickle the request in, finishing the head well within the timeout
+    for chunk in [
+        "GET / HTTP/1.1\r\n",
+        "Host: localhost\r\n",
+        "Connection: close\r\n",
+        "\r\n",
+    ] {
+        client.write_all(chunk.as_bytes()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    let mut response = String::new();
+    client.read_to_string(&mut response).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.ends_with("hello"));
+}
+
+#[tokio::test]
+async fn slow_download_outlasts_read_timeout() {
+    let dir = large_file_dir("slow-download");
+    let socket = common::start(
+        "slow-download",
+        serde_json::json!({ "read_timeout_secs": 1, "static_dir": dir }),
+    )
+    .await;
+    let mut client = common::connect(&socket).await;
+    client.write_all(LARGE_FILE_REQUEST).await.unwrap();
+
+    // Take in part of the body, then pause for longer than the read timeout
+    let mut head = vec![0; 64 * 1024];
+    client.read_exact(&mut head).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+
+    let mut response = head;
+    client.read_to_end(&mut response).await.unwrap();
+
+    assert!(response.starts_with(b"HTTP/1.1 200 OK"));
+    let body_start = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    assert_eq!(response.len() - body_start, LARGE_FILE_LEN);
+}
+
+#[tokio::test]
+async fn client_that_stops_reading_is_dropped_after_write_timeout() {
+    let dir = large_file_dir("write-timeout");
+    let socket = common::start(
+        "write-timeout",
+        serde_json::json!({ "write_timeout_secs": 1, "static_dir": dir }),
+    )
+    .await;
+    let mut client = common::connect(&socket).await;
+    client.write_all(LARGE_FILE_REQUEST).await.unwrap();
+
+    // Let the socket buffers fill up and the server's write stall
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+
+    let mut response = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+        .await
+        .expect("connection was not dropped")
+        .ok();
+
+    assert!(response.starts_with(b"HTTP/1.1 200 OK"));
+    assert!(response.len() < LARGE_FILE_LEN);
+}
+
+#[tokio::test]
+async fn stalled_client_is_dropped_after_read_timeout_with_h2c() {
+    let socket = common::start(
+        "read-timeout-h2c",
+        serde_json::json!({ "read_timeout_secs": 1, "h2c": true }),
+    )
+    .await;
+
+    // Silent, then stalling after the HTTP/2 preface
+    for preface in [&b""[..], b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"] {
+        let mut client = common::connect(&socket).await;
+        client.write_all(preface).await.unwrap();
+
+        let started = Instant::now();
+        let mut response = Vec::new();
+        tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+            .await
+            .expect("connection was not dropped")
+            .ok();
+
+        assert!(started.elapsed() >= Duration::from_millis(900));
+    }
+}
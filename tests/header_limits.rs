@@ -0,0 +1,53 @@
+#![cfg(unix)]
+
+mod common;
+
+#[tokio::test]
+async fn oversized_header_is_rejected_with_431() {
+    let socket = common::start(
+        "oversized-header",
+        serde_json::json!({ "max_header_bytes": 8192 }),
+    )
+    .await;
+
+    let request = format!(
+        "GET / HTTP/1.1\r\nHost: localhost\r\nX-Padding: {}\r\nConnection: close\r\n\r\n",
+        "a".repeat(16 * 1024)
+    );
+    let response = common::send(&socket, &request).await;
+
+    assert!(response.starts_with("HTTP/1.1 431"), "{}", response);
+}
+
+#[tokio::test]
+async fn too_many_headers_are_rejected_with_431() {
+    let socket = common::start("many-headers", serde_json::json!({ "max_headers": 10 })).await;
+
+    let headers: String = (0..20)
+        .map(|i| format!("X-Header-{}: {}\r\n", i, i))
+        .collect();
+    let request = format!(
+        "GET / HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+        headers
+    );
+    let response = common::send(&socket, &request).await;
+
+    assert!(response.starts_with("HTTP/1.1 431"), "{}", response);
+}
+
+#[tokio::test]
+async fn headers_within_limits_are_served() {
+    let socket = common::start(
+        "headers-within-limits",
+        serde_json::json!({ "max_header_bytes": 8192, "max_headers": 10 }),
+    )
+    .await;
+
+    let request = format!(
+        "GET / HTTP/1.1\r\nHost: localhost\r\nX-Padding: {}\r\nConnection: close\r\n\r\n",
+        "a".repeat(1024)
+    );
+    let response = common::send(&socket, &request).await;
+
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+}
@@ -0,0 +1,44 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::path::Path;
+use std::time::Duration;
+
+/// Sends a prior-knowledge HTTP/2 `GET /` and returns the status and body.
+async fn h2_get(socket: &Path) -> Result<(http::StatusCode, String), h2::Error> {
+    let stream = common::connect(socket).await;
+    let (mut client, connection) = h2::client::handshake(stream).await?;
+    tokio::spawn(connection);
+
+    let request = http::Request::get("http://localhost/").body(()).unwrap();
+    let (response, _) = client.send_request(request, true)?;
+    let (parts, mut body) = response.await?.into_parts();
+
+    let mut data = Vec::new();
+    while let Some(chunk) = body.data().await {
+        data.extend_from_slice(&chunk?);
+    }
+    Ok((parts.status, String::from_utf8(data).unwrap()))
+}
+
+#[tokio::test]
+async fn prior_knowledge_http2_is_served_with_h2c() {
+    let socket = common::start("h2c-enabled", serde_json::json!({ "h2c": true })).await;
+
+    let (status, body) = h2_get(&socket).await.unwrap();
+
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body, "hello");
+}
+
+#[tokio::test]
+async fn prior_knowledge_http2_is_refused_by_default() {
+    let socket = common::start("h2c-disabled", serde_json::json!({})).await;
+
+    let result = tokio::time::timeout(Duration::from_secs(5), h2_get(&socket))
+        .await
+        .expect("server neither answered nor closed the connection");
+
+    assert!(result.is_err(), "{:?}", result);
+}
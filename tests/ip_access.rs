@@ -0,0 +1,48 @@
+use binary_release_server::{run, Config};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+#[tokio::test]
+async fn rejected_request_gets_response_headers() {
+    // Access lists need a peer IP, so this one runs over TCP
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let config: Config = serde_json::from_value(serde_json::json!({
+        "message": "hello",
+        "port": port,
+        "deny_cidrs": ["127.0.0.0/8"],
+        "security_headers": true,
+        "response_headers": { "X-Served-By": "test" },
+    }))
+    .unwrap();
+    tokio::spawn(run(config));
+
+    let mut client = None;
+    for _ in 0..100 {
+        if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)).await {
+            client = Some(stream);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let mut client = client.expect("server did not start listening");
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).await.unwrap();
+    let response = response.to_ascii_lowercase();
+
+    assert!(response.starts_with("http/1.1 403"), "{}", response);
+    assert!(
+        response.contains("x-content-type-options: nosniff"),
+        "{}",
+        response
+    );
+    assert!(response.contains("x-served-by: test"), "{}", response);
+}
@@ -0,0 +1,45 @@
+#![cfg(unix)]
+
+mod common;
+
+#[tokio::test]
+async fn zero_request_timeout_disables_the_deadline() {
+    let socket = common::start(
+        "zero-request-timeout",
+        serde_json::json!({ "request_timeout_secs": 0 }),
+    )
+    .await;
+
+    let response = common::send(
+        &socket,
+        "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    )
+    .await;
+
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+}
+
+#[tokio::test]
+async fn health_checks_ignore_the_deadline() {
+    let socket = common::start("deadline-health", serde_json::json!({})).await;
+    // A deadline long in the past
+    let request = |path: &str| {
+        format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nX-Request-Deadline: 1\r\nConnection: close\r\n\r\n",
+            path
+        )
+    };
+
+    let response = common::send(&socket, &request("/")).await;
+    assert!(response.starts_with("HTTP/1.1 503"), "{}", response);
+
+    for path in ["/health", "/health/ready"] {
+        let response = common::send(&socket, &request(path)).await;
+        assert!(
+            response.starts_with("HTTP/1.1 200 OK"),
+            "{}: {}",
+            path,
+            response
+        );
+    }
+}
@@ -0,0 +1,146 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::path::PathBuf;
+
+/// A site directory with a stylesheet and a `docs/` index, next to a file that
+/// must not be reachable.
+fn site_dir(name: &str) -> PathBuf {
+    let root = common::socket_path(name).with_extension("site");
+    let site = root.join("site");
+    std::fs::create_dir_all(site.join("docs")).unwrap();
+    std::fs::write(root.join("secret.txt"), "secret").unwrap();
+    std::fs::write(site.join("app.css"), "body {}").unwrap();
+    std::fs::write(site.join("docs/index.html"), "<h1>docs</h1>").unwrap();
+    site
+}
+
+fn get(path: &str, extra_headers: &str) -> String {
+    format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+        path, extra_headers
+    )
+}
+
+fn header<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+    response
+        .split("\r\n\r\n")
+        .next()
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value)
+}
+
+#[tokio::test]
+async fn assets_are_served_with_cache_control() {
+    let socket = common::start(
+        "static-cache",
+        serde_json::json!({ "static_dir": site_dir("static-cache"), "static_max_age_secs": 60 }),
+    )
+    .await;
+
+    let response = common::send(&socket, &get("/static/app.css", "")).await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(response.ends_with("body {}"));
+    assert_eq!(
+        header(&response, "cache-control"),
+        Some("public, max-age=60")
+    );
+
+    // Revalidating keeps the max-age
+    let last_modified = header(&response, "last-modified").unwrap();
+    let response = common::send(
+        &socket,
+        &get(
+            "/static/app.css",
+            &format!("If-Modified-Since: {}\r\n", last_modified),
+        ),
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 304"), "{}", response);
+    assert_eq!(
+        header(&response, "cache-control"),
+        Some("public, max-age=60")
+    );
+
+    let response = common::send(&socket, &get("/static/missing.css", "")).await;
+    assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+    assert_eq!(header(&response, "cache-control"), None);
+}
+
+#[tokio::test]
+async fn traversal_outside_static_dir_is_rejected() {
+    let socket = common::start(
+        "static-traversal",
+        serde_json::json!({ "static_dir": site_dir("static-traversal") }),
+    )
+    .await;
+
+    for path in [
+        "/static/../secret.txt",
+        "/static/%2e%2e/secret.txt",
+        "/static/docs/%2e%2e/%2e%2e/secret.txt",
+        "/static/..%2fsecret.txt",
+    ] {
+        let response = common::send(&socket, &get(path, "")).await;
+        assert!(
+            !response.starts_with("HTTP/1.1 200"),
+            "{}: {}",
+            path,
+            response
+        );
+        assert!(!response.contains("secret"), "{}: {}", path, response);
+    }
+}
+
+#[tokio::test]
+async fn static_route_is_absent_without_static_dir() {
+    let socket = common::start("static-unset", serde_json::json!({})).await;
+
+    let response = common::send(&socket, &get("/static/app.css", "")).await;
+    assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+}
+
+#[tokio::test]
+async fn directory_index_is_reachable_in_every_trailing_slash_mode() {
+    for mode in ["strict", "redirect", "ignore"] {
+        let name = format!("static-index-{}", mode);
+        let socket = common::start(
+            &name,
+            serde_json::json!({ "static_dir": site_dir(&name), "trailing_slash": mode }),
+        )
+        .await;
+
+        // The redirect keeps the mount prefix
+        let response = common::send(&socket, &get("/static/docs", "")).await;
+        assert!(
+            response.starts_with("HTTP/1.1 307"),
+            "{}: {}",
+            mode,
+            response
+        );
+        assert_eq!(
+            header(&response, "location"),
+            Some("/static/docs/"),
+            "{}",
+            mode
+        );
+
+        let response = common::send(&socket, &get("/static/docs/", "")).await;
+        assert!(
+            response.starts_with("HTTP/1.1 200 OK"),
+            "{}: {}",
+            mode,
+            response
+        );
+        assert!(
+            response.ends_with("<h1>docs</h1>"),
+            "{}: {}",
+            mode,
+            response
+        );
+    }
+}
@@ -0,0 +1,41 @@
+#![cfg(unix)]
+
+mod common;
+
+use binary_release_server::run;
+use std::io;
+
+#[tokio::test]
+async fn socket_of_a_running_instance_is_not_taken_over() {
+    let socket = common::start("in-use", serde_json::json!({})).await;
+
+    let err = run(common::config(&socket, serde_json::json!({})))
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+
+    // The first instance still owns the socket and keeps serving
+    let response = common::send(
+        &socket,
+        "GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+}
+
+#[tokio::test]
+async fn stale_socket_is_replaced() {
+    let socket = common::socket_path("stale");
+    let _ = std::fs::remove_file(&socket);
+    // Bind and drop a listener, leaving a socket file nobody listens on
+    drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+
+    tokio::spawn(run(common::config(&socket, serde_json::json!({}))));
+
+    let response = common::send(
+        &socket,
+        "GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+}